@@ -0,0 +1,161 @@
+//! Syntax highlighting for the editor pane via `syntect`.
+//!
+//! `syntect::parsing::ParseState` threads scope context from one line to
+//! the next, so highlighting isn't embarrassingly parallel: line 40 can't
+//! be parsed correctly without first parsing lines 0..40. `Highlighter`
+//! caches the `(ParseState, HighlightState)` pair produced by each line so
+//! that editing line N only re-parses from N down, not the whole buffer.
+
+use color_eyre::{eyre::bail, Result};
+use ratatui::{
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+};
+use syntect::{
+    highlighting::{FontStyle, HighlightIterator, HighlightState, Style as SyntectStyle, ThemeSet},
+    parsing::{ParseState, ScopeStack, SyntaxReference, SyntaxSet},
+};
+
+const DEFAULT_THEME: &str = "base16-ocean.dark";
+
+pub struct Highlighter {
+    syntax_set: SyntaxSet,
+    theme_set: ThemeSet,
+    theme_name: String,
+    extension: Option<String>,
+    filetype: Option<String>,
+    cache: Vec<LineCache>,
+}
+
+struct LineCache {
+    text: String,
+    line: Line<'static>,
+    parse_state: ParseState,
+    highlight_state: HighlightState,
+}
+
+impl Default for Highlighter {
+    fn default() -> Self {
+        Self {
+            syntax_set: SyntaxSet::load_defaults_newlines(),
+            theme_set: ThemeSet::load_defaults(),
+            theme_name: DEFAULT_THEME.to_string(),
+            extension: None,
+            filetype: None,
+            cache: Vec::new(),
+        }
+    }
+}
+
+impl Highlighter {
+    /// Records the extension of the file backing the buffer (set on open,
+    /// see file I/O) so it can pick a syntax without an explicit
+    /// `:set ft=`.
+    pub fn set_extension(&mut self, extension: impl Into<String>) {
+        self.extension = Some(extension.into());
+        self.cache.clear();
+    }
+
+    /// Handles `:set ft=<name>`, overriding the extension-based guess.
+    pub fn set_filetype(&mut self, name: &str) -> Result<()> {
+        if self.syntax_set.find_syntax_by_token(name).is_none() {
+            bail!("unknown filetype: {name}");
+        }
+
+        self.filetype = Some(name.to_string());
+        self.cache.clear();
+        Ok(())
+    }
+
+    /// Handles the theme-selection command.
+    pub fn set_theme(&mut self, name: &str) -> Result<()> {
+        if !self.theme_set.themes.contains_key(name) {
+            bail!("unknown theme: {name}");
+        }
+
+        self.theme_name = name.to_string();
+        self.cache.clear();
+        Ok(())
+    }
+
+    fn syntax(&self) -> &SyntaxReference {
+        self.filetype
+            .as_deref()
+            .and_then(|name| self.syntax_set.find_syntax_by_token(name))
+            .or_else(|| {
+                self.extension
+                    .as_deref()
+                    .and_then(|ext| self.syntax_set.find_syntax_by_extension(ext))
+            })
+            .unwrap_or_else(|| self.syntax_set.find_syntax_plain_text())
+    }
+
+    /// Highlights `lines`, reusing cached spans for every line above the
+    /// first one that changed since the last call, and returns styled
+    /// `ratatui` lines ready to render.
+    ///
+    /// `TextArea` has no hook for per-span styling, so gloop renders the
+    /// editor body as a `Paragraph` built from these lines instead of
+    /// `editor.widget()`; `TextArea` still owns cursor/selection state.
+    pub fn highlight(&mut self, lines: &[String]) -> Vec<Line<'static>> {
+        let dirty_from = self
+            .cache
+            .iter()
+            .zip(lines)
+            .position(|(cached, text)| &cached.text != text)
+            .unwrap_or_else(|| self.cache.len().min(lines.len()));
+
+        self.cache.truncate(dirty_from);
+
+        let theme = &self.theme_set.themes[&self.theme_name];
+        let highlighter = syntect::highlighting::Highlighter::new(theme);
+
+        let (mut parse_state, mut highlight_state) = match self.cache.last() {
+            Some(last) => (last.parse_state.clone(), last.highlight_state.clone()),
+            None => (
+                ParseState::new(self.syntax()),
+                HighlightState::new(&highlighter, ScopeStack::new()),
+            ),
+        };
+
+        for text in &lines[dirty_from..] {
+            let ops = parse_state
+                .parse_line(text, &self.syntax_set)
+                .unwrap_or_default();
+
+            let spans: Vec<Span<'static>> =
+                HighlightIterator::new(&mut highlight_state, &ops, text, &highlighter)
+                    .map(|(style, piece)| Span::styled(piece.to_string(), to_style(style)))
+                    .collect();
+
+            self.cache.push(LineCache {
+                text: text.clone(),
+                line: Line::from(spans),
+                parse_state: parse_state.clone(),
+                highlight_state: highlight_state.clone(),
+            });
+        }
+
+        self.cache.iter().map(|cached| cached.line.clone()).collect()
+    }
+}
+
+fn to_style(style: SyntectStyle) -> Style {
+    let mut result = Style::default().fg(Color::Rgb(
+        style.foreground.r,
+        style.foreground.g,
+        style.foreground.b,
+    ));
+
+    if style.font_style.contains(FontStyle::BOLD) {
+        result = result.add_modifier(Modifier::BOLD);
+    }
+    if style.font_style.contains(FontStyle::ITALIC) {
+        result = result.add_modifier(Modifier::ITALIC);
+    }
+    if style.font_style.contains(FontStyle::UNDERLINE) {
+        result = result.add_modifier(Modifier::UNDERLINED);
+    }
+
+    result
+}
@@ -0,0 +1,448 @@
+//! Configurable keybindings: a registry of named [`Action`]s plus a table
+//! mapping `(mode, key)` to an action name. The defaults reproduce gloop's
+//! historical hjkl bindings; a TOML config file layered on top of `Keybinds`
+//! lets users remap anything without touching `handle_keyevent`.
+
+use std::{collections::HashMap, fs, path::PathBuf};
+
+use color_eyre::Result;
+use crossterm::event::{KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use serde::Deserialize;
+use tui_textarea::CursorMove;
+
+use crate::{App, Mode, VisualType};
+
+/// A key-bound action: mutate the app and report any failure through the
+/// returned `Result`. Unlike a `parse_command` error, which is shown in the
+/// status line, an action's error propagates out of `handle_keyevent`.
+pub type Action = fn(&mut App) -> Result<()>;
+
+/// The mode a binding applies to, ignoring `VisualType` so Character, Line
+/// and Block visual share one set of bindings (the action itself inspects
+/// `app.mode` when the three need to diverge).
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ModeKind {
+    Insert,
+    Normal,
+    Visual,
+    Command,
+}
+
+impl From<&Mode> for ModeKind {
+    fn from(mode: &Mode) -> Self {
+        match mode {
+            Mode::Insert => ModeKind::Insert,
+            Mode::Normal => ModeKind::Normal,
+            Mode::Visual(_) => ModeKind::Visual,
+            Mode::Command => ModeKind::Command,
+        }
+    }
+}
+
+/// Populates the registry of action names to the functions implementing
+/// them. `Keybinds` only ever stores the name; this is where the name is
+/// resolved to real behaviour.
+pub fn load_actions() -> HashMap<&'static str, Action> {
+    let mut actions: HashMap<&'static str, Action> = HashMap::new();
+
+    actions.insert("insert_mode", insert_mode);
+    actions.insert("append_mode", append_mode);
+    actions.insert("insert_at_line_start", insert_at_line_start);
+    actions.insert("append_at_line_end", append_at_line_end);
+    actions.insert("open_below", open_below);
+    actions.insert("open_above", open_above);
+    actions.insert("normal_mode", normal_mode);
+    actions.insert("command_mode", command_mode);
+    actions.insert("command_cancel", command_cancel);
+    actions.insert("command_history_prev", command_history_prev);
+    actions.insert("command_history_next", command_history_next);
+    actions.insert("visual_mode", visual_mode);
+    actions.insert("visual_line_mode", visual_line_mode);
+    actions.insert("visual_block_mode", visual_block_mode);
+    actions.insert("undo", undo);
+    actions.insert("redo", redo);
+    actions.insert("paste", paste);
+    actions.insert("move_char_left", move_char_left);
+    actions.insert("move_char_down", move_char_down);
+    actions.insert("move_char_up", move_char_up);
+    actions.insert("move_char_right", move_char_right);
+    actions.insert("move_word_back", move_word_back);
+    actions.insert("move_word_forward", move_word_forward);
+    actions.insert("move_line_start", move_line_start);
+    actions.insert("move_line_end", move_line_end);
+    actions.insert("yank_selection", yank_selection);
+    actions.insert("delete_selection", delete_selection);
+    actions.insert("change_selection", change_selection);
+
+    actions
+}
+
+fn insert_mode(app: &mut App) -> Result<()> {
+    app.mode = Mode::Insert;
+    Ok(())
+}
+
+fn append_mode(app: &mut App) -> Result<()> {
+    app.editor.move_cursor(CursorMove::Forward);
+    app.mode = Mode::Insert;
+    Ok(())
+}
+
+fn insert_at_line_start(app: &mut App) -> Result<()> {
+    app.editor.move_cursor(CursorMove::Head);
+    app.mode = Mode::Insert;
+    Ok(())
+}
+
+fn append_at_line_end(app: &mut App) -> Result<()> {
+    app.editor.move_cursor(CursorMove::End);
+    app.mode = Mode::Insert;
+    Ok(())
+}
+
+fn open_below(app: &mut App) -> Result<()> {
+    app.editor.move_cursor(CursorMove::End);
+    app.editor.insert_newline();
+    app.mode = Mode::Insert;
+    Ok(())
+}
+
+fn open_above(app: &mut App) -> Result<()> {
+    app.editor.move_cursor(CursorMove::Up);
+    app.editor.move_cursor(CursorMove::End);
+    app.editor.insert_newline();
+    app.mode = Mode::Insert;
+    Ok(())
+}
+
+fn normal_mode(app: &mut App) -> Result<()> {
+    app.editor.cancel_selection();
+    app.visual_anchor = None;
+    app.mode = Mode::Normal;
+    Ok(())
+}
+
+fn command_mode(app: &mut App) -> Result<()> {
+    app.mode = Mode::Command;
+    app.history_nav = None;
+    Ok(())
+}
+
+fn command_cancel(app: &mut App) -> Result<()> {
+    // Clear the buffer. (Doing it this way preserves the history.)
+    app.commandline.move_cursor(CursorMove::End);
+    app.commandline.delete_line_by_head();
+    app.mode = Mode::Normal;
+    app.history_nav = None;
+    Ok(())
+}
+
+/// Steps one entry further back into command history. On the first press,
+/// snapshots the command line as the search prefix and shows the newest
+/// matching entry; each subsequent press shows the next-older match.
+fn command_history_prev(app: &mut App) -> Result<()> {
+    let (prefix, index) = match app.history_nav.clone() {
+        Some((prefix, index)) => (prefix, index + 1),
+        None => (app.commandline.lines().first().cloned().unwrap_or_default(), 0),
+    };
+
+    let matches = app.matching_history(&prefix);
+    let Some(entry) = matches.get(index).cloned() else {
+        // No older match: leave the command line and nav state untouched.
+        return Ok(());
+    };
+
+    app.set_commandline_text(&entry);
+    app.history_nav = Some((prefix, index));
+    Ok(())
+}
+
+/// Steps one entry forward, toward newer command history, restoring the original
+/// (pre-navigation) command line once the user moves past the newest match.
+fn command_history_next(app: &mut App) -> Result<()> {
+    let Some((prefix, index)) = app.history_nav.take() else {
+        return Ok(());
+    };
+
+    if index == 0 {
+        app.set_commandline_text(&prefix);
+        return Ok(());
+    }
+
+    let index = index - 1;
+    let matches = app.matching_history(&prefix);
+    if let Some(entry) = matches.get(index) {
+        app.set_commandline_text(entry);
+    }
+    app.history_nav = Some((prefix, index));
+    Ok(())
+}
+
+fn visual_mode(app: &mut App) -> Result<()> {
+    app.editor.start_selection();
+    app.visual_anchor = Some(app.editor.cursor());
+    app.mode = Mode::Visual(VisualType::Character);
+    Ok(())
+}
+
+fn visual_line_mode(app: &mut App) -> Result<()> {
+    app.visual_anchor = Some(app.editor.cursor());
+    app.mode = Mode::Visual(VisualType::Line);
+    Ok(())
+}
+
+fn visual_block_mode(app: &mut App) -> Result<()> {
+    app.visual_anchor = Some(app.editor.cursor());
+    app.mode = Mode::Visual(VisualType::Block);
+    Ok(())
+}
+
+fn undo(app: &mut App) -> Result<()> {
+    _ = app.editor.undo();
+    Ok(())
+}
+
+fn redo(app: &mut App) -> Result<()> {
+    _ = app.editor.redo();
+    Ok(())
+}
+
+fn paste(app: &mut App) -> Result<()> {
+    _ = app.editor.paste();
+    Ok(())
+}
+
+fn move_char_left(app: &mut App) -> Result<()> {
+    app.editor.move_cursor(CursorMove::Back);
+    Ok(())
+}
+
+fn move_char_down(app: &mut App) -> Result<()> {
+    app.editor.move_cursor(CursorMove::Down);
+    Ok(())
+}
+
+fn move_char_up(app: &mut App) -> Result<()> {
+    app.editor.move_cursor(CursorMove::Up);
+    Ok(())
+}
+
+fn move_char_right(app: &mut App) -> Result<()> {
+    app.editor.move_cursor(CursorMove::Forward);
+    Ok(())
+}
+
+fn move_word_back(app: &mut App) -> Result<()> {
+    app.editor.move_cursor(CursorMove::WordBack);
+    Ok(())
+}
+
+fn move_word_forward(app: &mut App) -> Result<()> {
+    app.editor.move_cursor(CursorMove::WordForward);
+    Ok(())
+}
+
+fn move_line_start(app: &mut App) -> Result<()> {
+    app.editor.move_cursor(CursorMove::Head);
+    Ok(())
+}
+
+fn move_line_end(app: &mut App) -> Result<()> {
+    app.editor.move_cursor(CursorMove::End);
+    Ok(())
+}
+
+fn yank_selection(app: &mut App) -> Result<()> {
+    match app.mode {
+        Mode::Visual(VisualType::Character) => _ = app.editor.copy(),
+        Mode::Visual(VisualType::Line) => app.yank_visual_line(),
+        Mode::Visual(VisualType::Block) => app.yank_visual_block(),
+        _ => {}
+    }
+    app.visual_anchor = None;
+    app.mode = Mode::Normal;
+    Ok(())
+}
+
+fn delete_selection(app: &mut App) -> Result<()> {
+    match app.mode {
+        Mode::Visual(VisualType::Character) => _ = app.editor.cut(),
+        Mode::Visual(VisualType::Line) => app.cut_visual_line(),
+        Mode::Visual(VisualType::Block) => app.cut_visual_block(),
+        _ => {}
+    }
+    app.visual_anchor = None;
+    app.mode = Mode::Normal;
+    Ok(())
+}
+
+fn change_selection(app: &mut App) -> Result<()> {
+    match app.mode {
+        Mode::Visual(VisualType::Character) => _ = app.editor.cut(),
+        Mode::Visual(VisualType::Line) => app.cut_visual_line(),
+        Mode::Visual(VisualType::Block) => app.cut_visual_block(),
+        _ => {}
+    }
+    app.visual_anchor = None;
+    app.mode = Mode::Insert;
+    Ok(())
+}
+
+/// `(mode, key) -> action name` table, built from the defaults and then
+/// overridden from `~/.config/gloop/config.toml` if one is present.
+pub struct Keybinds {
+    bindings: HashMap<(ModeKind, KeyEvent), String>,
+}
+
+impl Keybinds {
+    /// Loads the built-in defaults, then layers the user's config file (if
+    /// any) on top. A missing or unparsable config file is not an error:
+    /// gloop simply falls back to the defaults for whatever it couldn't
+    /// apply.
+    pub fn load() -> Self {
+        let mut keybinds = Self::defaults();
+
+        if let Some(path) = config_path() {
+            if let Ok(contents) = fs::read_to_string(path) {
+                if let Ok(config) = toml::from_str::<ConfigFile>(&contents) {
+                    keybinds.apply(ModeKind::Insert, config.insert);
+                    keybinds.apply(ModeKind::Normal, config.normal);
+                    keybinds.apply(ModeKind::Visual, config.visual);
+                    keybinds.apply(ModeKind::Command, config.command);
+                }
+            }
+        }
+
+        keybinds
+    }
+
+    fn defaults() -> Self {
+        let mut bindings = HashMap::new();
+
+        let mut bind = |mode: ModeKind, key: &str, action: &str| {
+            if let Some(event) = parse_key_event(key) {
+                bindings.insert((mode, event), action.to_string());
+            }
+        };
+
+        bind(ModeKind::Normal, "i", "insert_mode");
+        bind(ModeKind::Normal, "a", "append_mode");
+        bind(ModeKind::Normal, "I", "insert_at_line_start");
+        bind(ModeKind::Normal, "A", "append_at_line_end");
+        bind(ModeKind::Normal, "o", "open_below");
+        bind(ModeKind::Normal, "O", "open_above");
+        bind(ModeKind::Normal, "v", "visual_mode");
+        bind(ModeKind::Normal, "V", "visual_line_mode");
+        bind(ModeKind::Normal, "C-v", "visual_block_mode");
+        bind(ModeKind::Normal, ";", "command_mode");
+        bind(ModeKind::Normal, "Space", "command_mode");
+        bind(ModeKind::Normal, "u", "undo");
+        bind(ModeKind::Normal, "U", "redo");
+        bind(ModeKind::Normal, "p", "paste");
+        bind(ModeKind::Normal, "h", "move_char_left");
+        bind(ModeKind::Normal, "j", "move_char_down");
+        bind(ModeKind::Normal, "k", "move_char_up");
+        bind(ModeKind::Normal, "l", "move_char_right");
+        bind(ModeKind::Normal, "b", "move_word_back");
+        bind(ModeKind::Normal, "w", "move_word_forward");
+        bind(ModeKind::Normal, "0", "move_line_start");
+        bind(ModeKind::Normal, "E", "move_line_end");
+
+        bind(ModeKind::Visual, "Esc", "normal_mode");
+        bind(ModeKind::Visual, "y", "yank_selection");
+        bind(ModeKind::Visual, "d", "delete_selection");
+        bind(ModeKind::Visual, "c", "change_selection");
+        bind(ModeKind::Visual, "h", "move_char_left");
+        bind(ModeKind::Visual, "j", "move_char_down");
+        bind(ModeKind::Visual, "k", "move_char_up");
+        bind(ModeKind::Visual, "l", "move_char_right");
+        bind(ModeKind::Visual, "b", "move_word_back");
+        bind(ModeKind::Visual, "w", "move_word_forward");
+        bind(ModeKind::Visual, "0", "move_line_start");
+        bind(ModeKind::Visual, "E", "move_line_end");
+
+        bind(ModeKind::Insert, "Esc", "normal_mode");
+        bind(ModeKind::Command, "Esc", "command_cancel");
+        bind(ModeKind::Command, "Up", "command_history_prev");
+        bind(ModeKind::Command, "Down", "command_history_next");
+
+        Self { bindings }
+    }
+
+    fn apply(&mut self, mode: ModeKind, overrides: HashMap<String, String>) {
+        for (key, action) in overrides {
+            if let Some(event) = parse_key_event(&key) {
+                self.bindings.insert((mode, event), action);
+            }
+        }
+    }
+
+    /// Looks up the action bound to `key` in `mode`, normalising
+    /// Press/Repeat so a held-down motion key keeps repeating the same
+    /// action (matching the old hardcoded `Press | Repeat` arms), and
+    /// stripping a redundant `SHIFT` modifier off `Char` keys so bindings
+    /// for uppercase letters (`V`, `I`, ...) still match on terminals that
+    /// report `SHIFT` alongside the already-uppercased char.
+    pub fn lookup(&self, mode: ModeKind, key: KeyEvent) -> Option<&str> {
+        if !matches!(key.kind, KeyEventKind::Press | KeyEventKind::Repeat) {
+            return None;
+        }
+
+        let mut modifiers = key.modifiers;
+        if matches!(key.code, KeyCode::Char(_)) {
+            modifiers.remove(KeyModifiers::SHIFT);
+        }
+
+        let event = KeyEvent {
+            kind: KeyEventKind::Press,
+            modifiers,
+            ..key
+        };
+
+        self.bindings.get(&(mode, event)).map(String::as_str)
+    }
+}
+
+/// The subset of `~/.config/gloop/config.toml` this module understands:
+/// one table per mode, each mapping a key string to an action name.
+#[derive(Deserialize, Default)]
+struct ConfigFile {
+    #[serde(default)]
+    insert: HashMap<String, String>,
+    #[serde(default)]
+    normal: HashMap<String, String>,
+    #[serde(default)]
+    visual: HashMap<String, String>,
+    #[serde(default)]
+    command: HashMap<String, String>,
+}
+
+fn config_path() -> Option<PathBuf> {
+    let home = std::env::var_os("HOME")?;
+    Some(PathBuf::from(home).join(".config/gloop/config.toml"))
+}
+
+/// Parses a key string like `"j"`, `"Esc"` or `"C-v"` into the `KeyEvent`
+/// it would produce. Only what gloop's own bindings need is supported.
+fn parse_key_event(key: &str) -> Option<KeyEvent> {
+    let (modifiers, rest) = match key.strip_prefix("C-") {
+        Some(rest) => (KeyModifiers::CONTROL, rest),
+        None => (KeyModifiers::NONE, key),
+    };
+
+    let code = match rest {
+        "Esc" => KeyCode::Esc,
+        "Enter" => KeyCode::Enter,
+        "Tab" => KeyCode::Tab,
+        "Space" => KeyCode::Char(' '),
+        "Backspace" => KeyCode::Backspace,
+        "Up" => KeyCode::Up,
+        "Down" => KeyCode::Down,
+        "Left" => KeyCode::Left,
+        "Right" => KeyCode::Right,
+        single if single.chars().count() == 1 => KeyCode::Char(single.chars().next()?),
+        _ => return None,
+    };
+
+    Some(KeyEvent::new(code, modifiers))
+}
@@ -1,13 +1,19 @@
 mod errors;
+mod highlight;
+mod keybinds;
 mod tui;
 
+use std::{collections::HashMap, path::PathBuf};
+
 use base64::prelude::*;
 use color_eyre::{
-    eyre::{bail, WrapErr},
+    eyre::{bail, eyre, WrapErr},
     owo_colors::OwoColorize,
     Result,
 };
-use crossterm::event::{KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use crossterm::event::{KeyCode, KeyEventKind};
+use highlight::Highlighter;
+use keybinds::{Action, Keybinds, ModeKind};
 use ratatui::{
     layout::Alignment,
     prelude::*,
@@ -28,6 +34,11 @@ fn main() -> Result<()> {
 
 const MAX_HISTORIES: usize = usize::MAX;
 
+/// Upper bound for a Normal/Visual repeat count (e.g. the `3` in `3j`),
+/// well past anything a real repeat would need, so accumulating digits
+/// can't overflow `u32`.
+const MAX_PENDING_COUNT: u32 = 9_999;
+
 #[derive(Default, PartialEq)]
 enum Mode {
     #[default]
@@ -50,6 +61,37 @@ struct App {
     editor: TextArea<'static>,
     commandline: TextArea<'static>,
     mode: Mode,
+    keybinds: Keybinds,
+    actions: HashMap<&'static str, Action>,
+    highlighter: Highlighter,
+    file_path: Option<PathBuf>,
+    /// The buffer's content as of the last open/write, used to derive `dirty`.
+    clean_snapshot: Vec<String>,
+    dirty: bool,
+    /// The last command's error, shown in the status line until the next
+    /// command runs.
+    status_message: Option<String>,
+    /// The `(row, col)` the cursor was at when the current Visual selection
+    /// was started. `Line` and `Block` visual compute their selection from
+    /// this and the live cursor position rather than `TextArea`'s own
+    /// (character-oriented) selection.
+    visual_anchor: Option<(usize, usize)>,
+    /// The repeat count being typed before a Normal/Visual motion (e.g. the
+    /// `3` in `3j`), shown in the status line until the action it prefixes
+    /// runs. Operators (`yank_selection`/`delete_selection`/
+    /// `change_selection`) aren't repeated — see `handle_keyevent`.
+    pending_count: Option<u32>,
+    /// Every command line executed so far, oldest first.
+    command_history: Vec<String>,
+    /// `Some((prefix, index))` while paging through `command_history` with
+    /// Up/Down: `prefix` is what the command line held before the first
+    /// Up, and `index` walks the newest-first list of history entries that
+    /// start with it.
+    history_nav: Option<(String, usize)>,
+    /// The first visible row of the editor pane, kept in step with the
+    /// cursor each frame so the viewport scrolls instead of the cursor
+    /// running off the bottom (or top) of the pane.
+    scroll_offset: usize,
 }
 
 impl Default for App {
@@ -60,11 +102,25 @@ impl Default for App {
         let mut commandline = TextArea::default();
         commandline.set_max_histories(MAX_HISTORIES);
 
+        let clean_snapshot = editor.lines().to_vec();
+
         Self {
             editor,
             commandline,
             should_exit: false,
             mode: Mode::default(),
+            keybinds: Keybinds::load(),
+            actions: keybinds::load_actions(),
+            highlighter: Highlighter::default(),
+            file_path: None,
+            clean_snapshot,
+            dirty: false,
+            status_message: None,
+            visual_anchor: None,
+            pending_count: None,
+            command_history: Vec::new(),
+            history_nav: None,
+            scroll_offset: 0,
         }
     }
 }
@@ -81,15 +137,19 @@ impl App {
     }
 
     fn render_frame(&mut self, frame: &mut Frame) {
-        let constraints = if self.mode == Mode::Command {
-            [Constraint::Percentage(100), Constraint::Min(3)]
+        let command_height = if self.mode == Mode::Command {
+            Constraint::Min(3)
         } else {
-            [Constraint::Percentage(100), Constraint::Max(0)]
+            Constraint::Max(0)
         };
 
         let app_layout = Layout::default()
             .direction(Direction::Vertical)
-            .constraints(constraints)
+            .constraints([
+                Constraint::Percentage(100),
+                command_height,
+                Constraint::Length(1),
+            ])
             .split(frame.size());
 
         let buffer_layout = Layout::default()
@@ -102,11 +162,98 @@ impl App {
             // .horizontal_margin(1)
             .split(app_layout[1]);
 
-        let widget = self.editor.widget();
-        frame.render_widget(widget, app_layout[0]);
+        let editor_block = Block::default()
+            .borders(Borders::TOP)
+            .border_type(BorderType::Double)
+            .padding(Padding::uniform(1))
+            .title(Span::from(" Gloop ").yellow())
+            .title_alignment(Alignment::Center);
+
+        let editor_area = editor_block.inner(app_layout[0]);
+        let gutter_width = self.editor.lines().len().max(1).to_string().len() + 1;
+
+        let (cursor_row, cursor_col) = self.editor.cursor();
+        self.scroll_to_cursor(cursor_row, editor_area.height as usize);
+
+        let mut lines = self.highlighter.highlight(self.editor.lines());
+        for (row, start_col, end_col) in self.visual_selection_ranges() {
+            if let Some(line) = lines.get_mut(row) {
+                *line = apply_selection(line.clone(), start_col, end_col);
+            }
+        }
+
+        let numbered_lines: Vec<Line> = lines
+            .into_iter()
+            .enumerate()
+            .map(|(i, line)| {
+                let number = Span::styled(
+                    format!("{:>width$} ", i + 1, width = gutter_width - 1),
+                    Style::default().fg(Color::Yellow),
+                );
+                let mut spans = vec![number];
+                spans.extend(line.spans);
+                Line::from(spans)
+            })
+            .collect();
+
+        frame.render_widget(
+            Paragraph::new(numbered_lines)
+                .block(editor_block)
+                .scroll((self.scroll_offset as u16, 0)),
+            app_layout[0],
+        );
+
+        if self.mode != Mode::Command {
+            frame.set_cursor(
+                editor_area.x + gutter_width as u16 + cursor_col as u16,
+                editor_area.y + (cursor_row - self.scroll_offset) as u16,
+            );
+        }
 
         let widget = self.commandline.widget();
         frame.render_widget(widget, buffer_layout[1]);
+
+        self.render_status_line(frame, app_layout[2]);
+    }
+
+    /// Renders the persistent bottom status bar: mode, cursor position,
+    /// dirty state, filename, and the last command's error (if any). Shown
+    /// in every mode, not only Command, so a mistyped command doesn't just
+    /// vanish when `Esc`/`Enter` return to Normal.
+    fn render_status_line(&self, frame: &mut Frame, area: Rect) {
+        let mode_label = match &self.mode {
+            Mode::Insert => "INSERT",
+            Mode::Normal => "NORMAL",
+            Mode::Visual(VisualType::Character) => "VISUAL",
+            Mode::Visual(VisualType::Line) => "V-LINE",
+            Mode::Visual(VisualType::Block) => "V-BLOCK",
+            Mode::Command => "COMMAND",
+        };
+
+        let (row, col) = self.editor.cursor();
+        let filename = self
+            .file_path
+            .as_ref()
+            .map(|path| path.display().to_string())
+            .unwrap_or_else(|| "[No Name]".to_string());
+
+        let mut spans = vec![
+            Span::styled(format!(" {mode_label} "), Style::default().reversed()),
+            Span::raw(format!(" {}:{} ", row + 1, col + 1)),
+            Span::raw(if self.dirty { "[+] " } else { "" }),
+            Span::raw(filename),
+        ];
+
+        if let Some(count) = self.pending_count {
+            spans.push(Span::raw(format!("  {count}")));
+        }
+
+        if let Some(message) = &self.status_message {
+            spans.push(Span::raw("  "));
+            spans.push(Span::styled(message.clone(), Style::default().fg(Color::Red)));
+        }
+
+        frame.render_widget(Paragraph::new(Line::from(spans)), area);
     }
 
     fn handle_events(&mut self) -> Result<()> {
@@ -116,177 +263,48 @@ impl App {
         }
     }
 
+    /// Looks up the action bound to `event` for the current mode and runs
+    /// it `pending_count.max(1)` times. Repetition only applies to actions
+    /// that stay in the same mode (motions like `j`/`w`): gloop has no
+    /// `dd`/`dw`-style operators, and the existing operators
+    /// (`yank_selection`/`delete_selection`/`change_selection`) already
+    /// leave Visual mode on their first run, so the loop stops the moment
+    /// the mode changes rather than spinning through no-op iterations.
+    /// Modes that also accept raw text (Insert, Command) fall back to
+    /// feeding the event straight to their `TextArea` when no binding
+    /// matches.
     fn handle_keyevent(&mut self, event: crossterm::event::KeyEvent) -> Result<()> {
-        match &self.mode {
-            Mode::Insert => match event {
-                crossterm::event::KeyEvent {
-                    code: KeyCode::Esc,
-                    kind: KeyEventKind::Press,
-                    ..
-                } => self.mode = Mode::Normal,
-
-                input => _ = self.editor.input(input),
-            },
-
-            Mode::Normal => match event {
-                crossterm::event::KeyEvent {
-                    code: KeyCode::Char('u'),
-                    kind: KeyEventKind::Press | KeyEventKind::Repeat,
-                    ..
-                } => _ = self.editor.undo(),
-
-                crossterm::event::KeyEvent {
-                    code: KeyCode::Char('U'),
-                    kind: KeyEventKind::Press | KeyEventKind::Repeat,
-                    ..
-                } => _ = self.editor.redo(),
-
-                crossterm::event::KeyEvent {
-                    code: KeyCode::Char('i'),
-                    kind: KeyEventKind::Press,
-                    ..
-                } => self.mode = Mode::Insert,
-
-                crossterm::event::KeyEvent {
-                    code: KeyCode::Char('a'),
-                    kind: KeyEventKind::Press,
-                    ..
-                } => {
-                    self.editor.move_cursor(CursorMove::Forward);
-                    self.mode = Mode::Insert;
-                }
-
-                crossterm::event::KeyEvent {
-                    code: KeyCode::Char('v'),
-                    kind: KeyEventKind::Press,
-                    modifiers: _modifiers,
-                    ..
-                } => {
-                    self.editor.start_selection();
-                    self.mode = Mode::Visual(VisualType::default());
-                }
-
-                crossterm::event::KeyEvent {
-                    code: KeyCode::Char(';') | KeyCode::Char(' '),
-                    kind: KeyEventKind::Press,
-                    ..
-                } => self.mode = Mode::Command,
-
-                crossterm::event::KeyEvent {
-                    code: KeyCode::Char('p'),
-                    kind: KeyEventKind::Press,
-                    ..
-                } => _ = self.editor.paste(),
-
-                crossterm::event::KeyEvent {
-                    code: KeyCode::Char('h'),
-                    kind: KeyEventKind::Press | KeyEventKind::Repeat,
-                    ..
-                } => self.editor.move_cursor(CursorMove::Back),
-
-                crossterm::event::KeyEvent {
-                    code: KeyCode::Char('j'),
-                    kind: KeyEventKind::Press | KeyEventKind::Repeat,
-                    ..
-                } => self.editor.move_cursor(CursorMove::Down),
-
-                crossterm::event::KeyEvent {
-                    code: KeyCode::Char('k'),
-                    kind: KeyEventKind::Press | KeyEventKind::Repeat,
-                    ..
-                } => self.editor.move_cursor(CursorMove::Up),
-
-                crossterm::event::KeyEvent {
-                    code: KeyCode::Char('l'),
-                    kind: KeyEventKind::Press | KeyEventKind::Repeat,
-                    ..
-                } => self.editor.move_cursor(CursorMove::Forward),
-
-                crossterm::event::KeyEvent {
-                    code: KeyCode::Char('b'),
-                    kind: KeyEventKind::Press | KeyEventKind::Repeat,
-                    ..
-                } => self.editor.move_cursor(CursorMove::WordBack),
-
-                crossterm::event::KeyEvent {
-                    code: KeyCode::Char('w'),
-                    kind: KeyEventKind::Press | KeyEventKind::Repeat,
-                    ..
-                } => self.editor.move_cursor(CursorMove::WordForward),
-
-                crossterm::event::KeyEvent {
-                    code: KeyCode::Char('E'),
-                    kind: KeyEventKind::Press,
-                    ..
-                } => self.editor.move_cursor(CursorMove::End),
-
-                crossterm::event::KeyEvent {
-                    code: KeyCode::Char('0'),
-                    kind: KeyEventKind::Press,
-                    ..
-                } => self.editor.move_cursor(CursorMove::Head),
-
-                crossterm::event::KeyEvent {
-                    code: KeyCode::Char('I'),
-                    kind: KeyEventKind::Press,
-                    ..
-                } => {
-                    self.editor.move_cursor(CursorMove::Head);
-                    self.mode = Mode::Insert;
-                }
-
-                crossterm::event::KeyEvent {
-                    code: KeyCode::Char('A'),
-                    kind: KeyEventKind::Press,
-                    ..
-                } => {
-                    self.editor.move_cursor(CursorMove::End);
-                    self.mode = Mode::Insert;
-                }
+        if matches!(self.mode, Mode::Normal | Mode::Visual(_)) && self.accumulate_count(event) {
+            return Ok(());
+        }
 
-                crossterm::event::KeyEvent {
-                    code: KeyCode::Char('o'),
-                    kind: KeyEventKind::Press,
-                    ..
-                } => {
-                    self.editor.move_cursor(CursorMove::End);
-                    self.editor.insert_newline();
-                    self.mode = Mode::Insert;
-                }
-
-                crossterm::event::KeyEvent {
-                    code: KeyCode::Char('O'),
-                    kind: KeyEventKind::Press,
-                    ..
-                } => {
-                    self.editor.move_cursor(CursorMove::Up);
-                    self.editor.move_cursor(CursorMove::End);
-                    self.editor.insert_newline();
-                    self.mode = Mode::Insert;
+        let mode = ModeKind::from(&self.mode);
+        let action = self
+            .keybinds
+            .lookup(mode, event)
+            .and_then(|name| self.actions.get(name).copied());
+
+        if let Some(action) = action {
+            let count = self.pending_count.take().unwrap_or(1).max(1);
+            for _ in 0..count {
+                action(self)?;
+                if ModeKind::from(&self.mode) != mode {
+                    break;
                 }
+            }
+            return Ok(());
+        }
 
-                _ => {}
-            },
+        match &self.mode {
+            Mode::Insert => _ = self.editor.input(event),
 
             Mode::Command => match event {
-                crossterm::event::KeyEvent {
-                    code: KeyCode::Esc,
-                    kind: KeyEventKind::Press,
-                    ..
-                } => {
-                    // Clear the buffer. (Doing it this way preserves the history.)
-                    self.commandline.move_cursor(CursorMove::End);
-                    self.commandline.delete_line_by_head();
-
-                    self.mode = Mode::Normal;
-                }
-
                 crossterm::event::KeyEvent {
                     code: KeyCode::Enter,
                     kind: KeyEventKind::Press,
                     ..
                 } => {
-                    self.parse_command()?;
+                    self.status_message = self.parse_command().err().map(|err| err.to_string());
 
                     // Clear the buffer. (Doing it this way preserves the history.)
                     self.commandline.move_cursor(CursorMove::End);
@@ -298,148 +316,55 @@ impl App {
                 input => _ = self.commandline.input(input),
             },
 
-            Mode::Visual(visual_type) => match event {
-                KeyEvent {
-                    code: KeyCode::Esc,
-                    kind: KeyEventKind::Press,
-                    ..
-                } => {
-                    self.editor.cancel_selection();
-                    self.mode = Mode::Normal;
-                }
-
-                KeyEvent {
-                    code: KeyCode::Char('y'),
-                    kind: KeyEventKind::Press,
-                    ..
-                } => {
-                    self.editor.copy();
-                    self.mode = Mode::Normal;
-                }
-
-                KeyEvent {
-                    code: KeyCode::Char('d'),
-                    kind: KeyEventKind::Press,
-                    ..
-                } => {
-                    self.editor.cut();
-                    self.mode = Mode::Normal;
-                }
-
-                KeyEvent {
-                    code: KeyCode::Char('c'),
-                    kind: KeyEventKind::Press,
-                    ..
-                } => {
-                    self.editor.cut();
-                    self.mode = Mode::Insert;
-                }
-
-                KeyEvent {
-                    code: KeyCode::Char('h'),
-                    kind: KeyEventKind::Press,
-                    ..
-                } => match visual_type {
-                    VisualType::Character => self.editor.move_cursor(CursorMove::Back),
-                    VisualType::Line => {}
-                    VisualType::Block => {}
-                },
-
-                KeyEvent {
-                    code: KeyCode::Char('j'),
-                    kind: KeyEventKind::Press,
-                    ..
-                } => match visual_type {
-                    VisualType::Character => self.editor.move_cursor(CursorMove::Down),
-                    VisualType::Line => {}
-                    VisualType::Block => {}
-                },
-
-                KeyEvent {
-                    code: KeyCode::Char('k'),
-                    kind: KeyEventKind::Press,
-                    ..
-                } => match visual_type {
-                    VisualType::Character => self.editor.move_cursor(CursorMove::Up),
-                    VisualType::Line => {}
-                    VisualType::Block => {}
-                },
-
-                KeyEvent {
-                    code: KeyCode::Char('l'),
-                    kind: KeyEventKind::Press,
-                    ..
-                } => match visual_type {
-                    VisualType::Character => self.editor.move_cursor(CursorMove::Forward),
-                    VisualType::Line => {}
-                    VisualType::Block => {}
-                },
+            // An unbound key cancels any pending count, same as Vim: a
+            // count only survives up to the motion/operator it prefixes.
+            Mode::Normal | Mode::Visual(_) => self.pending_count = None,
+        }
 
-                KeyEvent {
-                    code: KeyCode::Char('E'),
-                    kind: KeyEventKind::Press,
-                    ..
-                } => self.editor.move_cursor(CursorMove::End),
+        Ok(())
+    }
 
-                KeyEvent {
-                    code: KeyCode::Char('0'),
-                    kind: KeyEventKind::Press,
-                    ..
-                } => self.editor.move_cursor(CursorMove::Head),
+    /// Folds a digit key into `pending_count` and reports whether `event`
+    /// was consumed as one. A lone `0` is left alone — it's the
+    /// go-to-line-start motion — unless a count is already pending, in
+    /// which case it extends it (`10`, `20`, ...).
+    fn accumulate_count(&mut self, event: crossterm::event::KeyEvent) -> bool {
+        if !matches!(event.kind, KeyEventKind::Press | KeyEventKind::Repeat) {
+            return false;
+        }
 
-                KeyEvent {
-                    code: KeyCode::Char('b'),
-                    kind: KeyEventKind::Press,
-                    ..
-                } => match visual_type {
-                    VisualType::Character => self.editor.move_cursor(CursorMove::WordBack),
-                    VisualType::Line => {}
-                    VisualType::Block => {}
-                },
+        match event.code {
+            KeyCode::Char(digit @ '1'..='9') => {
+                let digit = digit.to_digit(10).unwrap();
+                let count = self
+                    .pending_count
+                    .unwrap_or(0)
+                    .saturating_mul(10)
+                    .saturating_add(digit)
+                    .min(MAX_PENDING_COUNT);
+                self.pending_count = Some(count);
+                true
+            }
 
-                KeyEvent {
-                    code: KeyCode::Char('w'),
-                    kind: KeyEventKind::Press,
-                    ..
-                } => match visual_type {
-                    VisualType::Character => self.editor.move_cursor(CursorMove::WordForward),
-                    VisualType::Line => {}
-                    VisualType::Block => {}
-                },
+            KeyCode::Char('0') if self.pending_count.is_some() => {
+                let count = self
+                    .pending_count
+                    .unwrap()
+                    .saturating_mul(10)
+                    .min(MAX_PENDING_COUNT);
+                self.pending_count = Some(count);
+                true
+            }
 
-                _ => {}
-            },
+            _ => false,
         }
-
-        Ok(())
     }
 
     fn update(&mut self) {
-        self.update_editor();
+        self.dirty = self.editor.lines() != self.clean_snapshot.as_slice();
         self.update_commandline();
     }
 
-    fn update_editor(&mut self) {
-        if self.mode == Mode::Command {
-            self.editor
-                .set_cursor_style(Style::default().bg(Color::DarkGray));
-        } else {
-            self.editor.set_cursor_style(Style::default().reversed());
-        }
-
-        self.editor
-            .set_line_number_style(Style::default().fg(Color::Yellow));
-
-        let block = Block::default()
-            .borders(Borders::TOP)
-            .border_type(BorderType::Double)
-            .padding(Padding::uniform(1))
-            .title(Span::from(" Gloop ").yellow())
-            .title_alignment(Alignment::Center);
-
-        self.editor.set_block(block);
-    }
-
     fn update_commandline(&mut self) {
         self.commandline.set_cursor_line_style(Style::default());
 
@@ -461,18 +386,46 @@ impl App {
         }
 
         if let Some(line) = lines.first() {
+            if !line.is_empty() {
+                self.command_history.push(line.clone());
+            }
+
             match line.as_str().split_whitespace().next().unwrap_or_default() {
-                "q" => self.should_exit = true,
+                "q" => {
+                    if self.dirty {
+                        bail!("unsaved changes (use q! to discard)");
+                    }
+                    self.should_exit = true;
+                }
+
+                "q!" => self.should_exit = true,
+
+                "e" => {
+                    let path = line.as_str().split_whitespace().nth(1);
+                    let Some(path) = path else {
+                        bail!("usage: e <path>");
+                    };
+                    self.open_file(PathBuf::from(path))?;
+                }
+
+                "w" => {
+                    let path = line.as_str().split_whitespace().nth(1).map(PathBuf::from);
+                    self.write_file(path)?;
+                }
+
+                "wq" | "x" => {
+                    self.write_file(None)?;
+                    self.should_exit = true;
+                }
 
                 "json" => match line.as_str().split_whitespace().nth(1).unwrap_or_default() {
                     "format" => {
                         let ugly_json = self.editor.lines().join("\n");
+                        let pretty_json =
+                            jsonxf::pretty_print(&ugly_json).map_err(|err| eyre!(err))?;
 
                         self.editor.select_all();
                         self.editor.cut();
-
-                        let pretty_json = jsonxf::pretty_print(&ugly_json).unwrap();
-
                         self.editor.insert_str(pretty_json);
                     }
 
@@ -504,10 +457,292 @@ impl App {
                     _ => bail!("unknown command: {}", line),
                 },
 
+                "set" => match line.as_str().split_whitespace().nth(1).unwrap_or_default() {
+                    setting if setting.starts_with("ft=") => {
+                        self.highlighter.set_filetype(&setting["ft=".len()..])?;
+                    }
+
+                    _ => bail!("unknown command: {}", line),
+                },
+
+                "theme" => {
+                    let name = line.as_str().split_whitespace().nth(1).unwrap_or_default();
+                    self.highlighter.set_theme(name)?;
+                }
+
                 _ => bail!("unknown command: {}", line),
             }
         }
 
         Ok(())
     }
+
+    /// Loads `path` into the editor, replacing its current contents, and
+    /// hands the extension to the highlighter so it can pick a syntax
+    /// automatically. `json format` does not consult the extension.
+    fn open_file(&mut self, path: PathBuf) -> Result<()> {
+        let contents = std::fs::read_to_string(&path)
+            .wrap_err_with(|| format!("failed to open {}", path.display()))?;
+
+        self.editor.select_all();
+        self.editor.cut();
+        self.editor.insert_str(contents);
+
+        if let Some(extension) = path.extension().and_then(|ext| ext.to_str()) {
+            self.highlighter.set_extension(extension);
+        }
+
+        self.clean_snapshot = self.editor.lines().to_vec();
+        self.dirty = false;
+        self.file_path = Some(path);
+
+        Ok(())
+    }
+
+    /// Writes the editor's contents to `path`, or to the file the buffer
+    /// was opened from if `path` is `None`.
+    fn write_file(&mut self, path: Option<PathBuf>) -> Result<()> {
+        let path = match path.or_else(|| self.file_path.clone()) {
+            Some(path) => path,
+            None => bail!("no file name"),
+        };
+
+        std::fs::write(&path, self.editor.lines().join("\n"))
+            .wrap_err_with(|| format!("failed to write {}", path.display()))?;
+
+        self.clean_snapshot = self.editor.lines().to_vec();
+        self.dirty = false;
+        self.file_path = Some(path);
+
+        Ok(())
+    }
+
+    /// Keeps `scroll_offset` in step with `cursor_row` so the cursor always
+    /// falls within the `visible_rows` of the editor pane, scrolling the
+    /// viewport instead of letting the cursor run off the top or bottom.
+    fn scroll_to_cursor(&mut self, cursor_row: usize, visible_rows: usize) {
+        if cursor_row < self.scroll_offset {
+            self.scroll_offset = cursor_row;
+        } else if visible_rows > 0 && cursor_row >= self.scroll_offset + visible_rows {
+            self.scroll_offset = cursor_row - visible_rows + 1;
+        }
+    }
+
+    /// `(row, start_col, end_col)` (end exclusive) touched by the current
+    /// Visual selection, one entry per spanned row, empty outside Visual
+    /// mode. Used to paint the selection ourselves, since the editor body
+    /// renders through a `Paragraph` built from highlighted spans rather
+    /// than `TextArea::widget()`, which would otherwise draw it for us.
+    fn visual_selection_ranges(&self) -> Vec<(usize, usize, usize)> {
+        let Mode::Visual(visual_type) = &self.mode else {
+            return Vec::new();
+        };
+
+        match visual_type {
+            VisualType::Line => {
+                let (start, end) = self.visual_line_range();
+                (start..=end)
+                    .map(|row| (row, 0, self.editor.lines()[row].chars().count()))
+                    .collect()
+            }
+
+            VisualType::Block => {
+                let ((top, left), (bottom, right)) = self.visual_block_range();
+                (top..=bottom).map(|row| (row, left, right + 1)).collect()
+            }
+
+            VisualType::Character => {
+                let anchor = self.visual_anchor.unwrap_or((0, 0));
+                let cursor = self.editor.cursor();
+                let (start, end) = if anchor <= cursor {
+                    (anchor, cursor)
+                } else {
+                    (cursor, anchor)
+                };
+
+                if start.0 == end.0 {
+                    return vec![(start.0, start.1, end.1 + 1)];
+                }
+
+                let mut ranges =
+                    vec![(start.0, start.1, self.editor.lines()[start.0].chars().count())];
+                ranges.extend(
+                    (start.0 + 1..end.0)
+                        .map(|row| (row, 0, self.editor.lines()[row].chars().count())),
+                );
+                ranges.push((end.0, 0, end.1 + 1));
+                ranges
+            }
+        }
+    }
+
+    /// The inclusive row range spanned by a Visual-Line selection, between
+    /// `visual_anchor` and the live cursor, normalized so `.0 <= .1`.
+    fn visual_line_range(&self) -> (usize, usize) {
+        let anchor_row = self.visual_anchor.map_or(0, |(row, _)| row);
+        let cursor_row = self.editor.cursor().0;
+        (anchor_row.min(cursor_row), anchor_row.max(cursor_row))
+    }
+
+    /// The rectangular `(row, col)` bounds of a Visual-Block selection,
+    /// between `visual_anchor` and the live cursor, normalized so the first
+    /// pair is the top-left corner.
+    fn visual_block_range(&self) -> ((usize, usize), (usize, usize)) {
+        let anchor = self.visual_anchor.unwrap_or((0, 0));
+        let cursor = self.editor.cursor();
+        let top = anchor.0.min(cursor.0);
+        let bottom = anchor.0.max(cursor.0);
+        let left = anchor.1.min(cursor.1);
+        let right = anchor.1.max(cursor.1);
+        ((top, left), (bottom, right))
+    }
+
+    /// Copies the selected lines into the editor's clipboard without
+    /// touching the buffer.
+    fn yank_visual_line(&mut self) {
+        let (start, end) = self.visual_line_range();
+        let text = self.editor.lines()[start..=end].join("\n") + "\n";
+        self.editor.set_yank_text(text);
+    }
+
+    /// Removes the selected lines from the buffer, stashing them in the
+    /// editor's clipboard first. Rebuilds the buffer wholesale the same way
+    /// `json format`/`base64` do, since `TextArea` has no "delete a line
+    /// range" primitive.
+    fn cut_visual_line(&mut self) {
+        let (start, end) = self.visual_line_range();
+        let removed = self.editor.lines()[start..=end].join("\n") + "\n";
+
+        let mut lines = self.editor.lines().to_vec();
+        lines.drain(start..=end);
+        if lines.is_empty() {
+            lines.push(String::new());
+        }
+
+        self.editor.select_all();
+        self.editor.cut();
+        self.editor.insert_str(lines.join("\n"));
+        self.editor.set_yank_text(removed);
+    }
+
+    /// Copies the rectangular column range spanned by each selected row
+    /// into the editor's clipboard, joined with newlines, without touching
+    /// the buffer.
+    fn yank_visual_block(&mut self) {
+        let ((top, left), (bottom, right)) = self.visual_block_range();
+        let lines = self.editor.lines();
+        let text = lines[top..=bottom]
+            .iter()
+            .map(|line| block_col_slice(line, left, right))
+            .collect::<Vec<_>>()
+            .join("\n");
+        self.editor.set_yank_text(text);
+    }
+
+    /// Removes the rectangular column range spanned by each selected row,
+    /// stashing the removed block in the editor's clipboard first.
+    fn cut_visual_block(&mut self) {
+        let ((top, left), (bottom, right)) = self.visual_block_range();
+        let mut lines = self.editor.lines().to_vec();
+
+        let removed = lines[top..=bottom]
+            .iter()
+            .map(|line| block_col_slice(line, left, right))
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        for line in &mut lines[top..=bottom] {
+            remove_block_col_slice(line, left, right);
+        }
+
+        self.editor.select_all();
+        self.editor.cut();
+        self.editor.insert_str(lines.join("\n"));
+        self.editor.set_yank_text(removed);
+    }
+
+    /// `command_history` entries starting with `prefix`, newest first.
+    fn matching_history(&self, prefix: &str) -> Vec<String> {
+        self.command_history
+            .iter()
+            .rev()
+            .filter(|entry| entry.starts_with(prefix))
+            .cloned()
+            .collect()
+    }
+
+    /// Replaces the command line's contents with `text`. (Doing it this way
+    /// preserves the history, same as the Enter handler's clear.)
+    fn set_commandline_text(&mut self, text: &str) {
+        self.commandline.move_cursor(CursorMove::End);
+        self.commandline.delete_line_by_head();
+        self.commandline.insert_str(text);
+    }
+}
+
+/// Extracts the `[left, right]` column slice of `line` (inclusive),
+/// treating columns past the line's end as empty. Used by Visual Block
+/// operations, whose spanned rows may be shorter than the block's right
+/// edge.
+fn block_col_slice(line: &str, left: usize, right: usize) -> String {
+    line.chars().skip(left).take(right - left + 1).collect()
+}
+
+/// Removes the `[left, right]` column slice from `line` in place, applying
+/// the same "missing columns are empty" rule as [`block_col_slice`].
+fn remove_block_col_slice(line: &mut String, left: usize, right: usize) {
+    let chars: Vec<char> = line.chars().collect();
+    if left >= chars.len() {
+        return;
+    }
+    let end = (right + 1).min(chars.len());
+    *line = chars[..left].iter().chain(&chars[end..]).collect();
+}
+
+/// Re-styles `line` so the columns in `[start_col, end_col)` render
+/// reverse-video, splitting spans as needed while preserving each span's
+/// underlying style. Paints the Visual-mode selection that `Paragraph`
+/// doesn't draw for us.
+fn apply_selection(line: Line<'static>, start_col: usize, end_col: usize) -> Line<'static> {
+    if start_col >= end_col {
+        return line;
+    }
+
+    let mut spans = Vec::new();
+    let mut col = 0;
+
+    for span in line.spans {
+        let len = span.content.chars().count();
+        let span_start = col;
+        let span_end = col + len;
+        col = span_end;
+
+        if span_end <= start_col || span_start >= end_col {
+            spans.push(span);
+            continue;
+        }
+
+        let chars: Vec<char> = span.content.chars().collect();
+        let sel_start = start_col.saturating_sub(span_start).min(len);
+        let sel_end = end_col.saturating_sub(span_start).min(len);
+
+        if sel_start > 0 {
+            spans.push(Span::styled(
+                chars[..sel_start].iter().collect::<String>(),
+                span.style,
+            ));
+        }
+        spans.push(Span::styled(
+            chars[sel_start..sel_end].iter().collect::<String>(),
+            span.style.reversed(),
+        ));
+        if sel_end < len {
+            spans.push(Span::styled(
+                chars[sel_end..].iter().collect::<String>(),
+                span.style,
+            ));
+        }
+    }
+
+    Line::from(spans)
 }